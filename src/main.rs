@@ -1,10 +1,13 @@
 use std::cmp::min;
+use std::collections::HashMap;
 use std::fs;
-use std::io::{self, Read};
+use std::io::{self, BufRead, Read};
 use std::path::{Path, PathBuf};
+use std::time::Instant;
 
 use anyhow::{bail, Context, Result};
-use serde::Deserialize;
+use log::{debug, info, trace, warn};
+use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Deserialize)]
 struct LLMResponse {
@@ -65,49 +68,640 @@ struct Change {
     #[serde(flatten)]
     command: Command,
     reason: String,
+    /// Hash of the first 4096 bytes of the file as the LLM saw it, checked first
+    /// since it's cheap; only consulted when present.
+    #[serde(default)]
+    partial_hash: Option<String>,
+    /// Hash of the whole file as the LLM saw it, checked after `partial_hash` (when
+    /// present) has matched, to confirm the file is byte-for-byte unchanged.
+    #[serde(default)]
+    file_hash: Option<String>,
+}
+
+const PARTIAL_HASH_BYTES: usize = 4096;
+
+/// A portable content hash, reproducible by anything that can compute blake3
+/// (including the LLM producing `partial_hash`/`file_hash`), unlike std's
+/// keyed/unspecified `DefaultHasher`.
+fn hash_bytes(data: &[u8]) -> String {
+    blake3::hash(data).to_hex().to_string()
+}
+
+fn partial_hash(content: &str) -> String {
+    let bytes = content.as_bytes();
+    let limit = bytes.len().min(PARTIAL_HASH_BYTES);
+    hash_bytes(&bytes[..limit])
+}
+
+fn full_hash(content: &str) -> String {
+    hash_bytes(content.as_bytes())
+}
+
+/// Verify `change`'s optional `partial_hash`/`file_hash` against the file's
+/// current `content`. Returns `Some(reason)` if the file has drifted since the
+/// LLM's snapshot was taken, or `None` if the change is unguarded or still matches.
+fn check_stale_context(change: &Change, content: &str) -> Option<String> {
+    if let Some(expected) = &change.partial_hash {
+        if &partial_hash(content) != expected {
+            return Some(format!(
+                "file changed since context was captured: {} (partial hash mismatch)",
+                change.filename.display()
+            ));
+        }
+    }
+
+    if let Some(expected) = &change.file_hash {
+        if &full_hash(content) != expected {
+            return Some(format!(
+                "file changed since context was captured: {} (full hash mismatch)",
+                change.filename.display()
+            ));
+        }
+    }
+
+    None
+}
+
+/// Flags controlling whether changes are written to disk or merely previewed.
+struct CliArgs {
+    /// Never write to disk; only show what would change.
+    dry_run: bool,
+    /// Render previews as a unified diff instead of a plain summary.
+    diff: bool,
+    /// Prompt for confirmation before writing each change.
+    confirm: bool,
+    /// Number of surrounding context lines to show in unified diffs.
+    context: usize,
+    /// Skip the pre-batch snapshot/rollback transaction, matching the old behavior.
+    no_rollback: bool,
+    /// Emit a single machine-readable JSON report on stdout in place of the plain
+    /// explanation and conclusion text. Log output, dry-run/diff previews and
+    /// confirmation prompts are still printed as text.
+    json: bool,
+    /// Re-apply a file already saved under `llm2fs_changes/` instead of reading a
+    /// fresh response from stdin, identified by timestamp or filename.
+    replay: Option<String>,
+    /// Change-file paths to read instead of stdin; when more than one is given,
+    /// their change lists are merged, in order.
+    inputs: Vec<PathBuf>,
+}
+
+fn parse_args() -> CliArgs {
+    let mut args = CliArgs {
+        dry_run: false,
+        diff: false,
+        confirm: false,
+        context: 3,
+        no_rollback: false,
+        json: false,
+        replay: None,
+        inputs: Vec::new(),
+    };
+
+    let mut iter = std::env::args().skip(1);
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--dry-run" => args.dry_run = true,
+            "--diff" => args.diff = true,
+            "--confirm" => args.confirm = true,
+            "--no-rollback" => args.no_rollback = true,
+            "--json" => args.json = true,
+            "--context" => {
+                if let Some(value) = iter.next() {
+                    args.context = value.parse().unwrap_or(3);
+                }
+            }
+            "--replay" => args.replay = iter.next(),
+            _ => args.inputs.push(PathBuf::from(arg)),
+        }
+    }
+
+    args
+}
+
+/// Joins (and, on replay, re-splits) multiple saved raw inputs within a single
+/// `llm2fs_changes/*.txt` file. A lone input round-trips unchanged, since
+/// joining/splitting a one-element list is a no-op.
+const INPUT_SEPARATOR: &str = "\n~~~llm2fs-input~~~\n";
+
+/// Resolve a `--replay` argument to a path under `llm2fs_changes/`. Accepts a
+/// bare timestamp (`2026-07-26-10-00-00`), a filename (`...-00.txt`), or a full
+/// path to a file already inside the directory.
+fn resolve_replay_path(name: &str) -> PathBuf {
+    let changes_dir = Path::new("llm2fs_changes");
+    let candidate = Path::new(name);
+    if candidate.is_absolute() || candidate.starts_with(changes_dir) {
+        candidate.to_path_buf()
+    } else if name.ends_with(".txt") {
+        changes_dir.join(name)
+    } else {
+        changes_dir.join(format!("{name}.txt"))
+    }
+}
+
+/// Combine several `LLMResponse`s read from separate input files into one,
+/// concatenating their explanations and change lists in order and keeping the
+/// last conclusion. Duplicate changes (as keyed by `dedupe_changes`) are
+/// deduped, keeping only the most recent.
+fn merge_responses(responses: Vec<LLMResponse>) -> LLMResponse {
+    let mut explanation_parts = Vec::new();
+    let mut conclusion = String::new();
+    let mut changes = Vec::new();
+
+    for response in responses {
+        if !response.explanation.is_empty() {
+            explanation_parts.push(response.explanation);
+        }
+        conclusion = response.conclusion;
+        changes.extend(response.changes);
+    }
+
+    LLMResponse {
+        explanation: explanation_parts.join("\n"),
+        changes: dedupe_changes(changes),
+        conclusion,
+    }
+}
+
+/// Keep only the most recent change for each (filename, fingerprint) pair, so
+/// that the exact same edit appearing in more than one merged input file isn't
+/// applied twice; the surviving occurrence's original position otherwise sets
+/// the order. Distinct edits to the same file (e.g. two different insertions)
+/// are not conflicts and both survive.
+fn dedupe_changes(changes: Vec<Change>) -> Vec<Change> {
+    let mut last_seen: HashMap<(PathBuf, String), usize> = HashMap::new();
+    for (i, change) in changes.iter().enumerate() {
+        last_seen.insert(
+            (change.filename.clone(), change_fingerprint(&change.command)),
+            i,
+        );
+    }
+
+    changes
+        .into_iter()
+        .enumerate()
+        .filter(|(i, change)| {
+            last_seen.get(&(change.filename.clone(), change_fingerprint(&change.command)))
+                == Some(i)
+        })
+        .map(|(_, change)| change)
+        .collect()
+}
+
+/// A key identifying what a change does, for dedup purposes. Single-shot,
+/// whole-file commands (create/rename/delete) conflict with another command of
+/// the same kind on the same filename, whatever its content;
+/// marker-based edits only conflict with another edit carrying identical
+/// marker/insert/delete content, since a file can legitimately receive several
+/// distinct insertions or deletions.
+fn change_fingerprint(command: &Command) -> String {
+    match command {
+        Command::InsertAfter {
+            insert_lines,
+            marker_lines,
+        } => format!(
+            "INSERT_AFTER:{:?}:{:?}",
+            marker_lines.lines(),
+            insert_lines.lines()
+        ),
+        Command::InsertBefore {
+            insert_lines,
+            marker_lines,
+        } => format!(
+            "INSERT_BEFORE:{:?}:{:?}",
+            marker_lines.lines(),
+            insert_lines.lines()
+        ),
+        Command::Delete { delete_lines } => format!("DELETE:{:?}", delete_lines.lines()),
+        Command::CreateFile { .. } | Command::RenameFile { .. } | Command::DeleteFile => {
+            command_tag(command).to_string()
+        }
+    }
+}
+
+/// Backs up every file a batch of changes is about to touch so the whole run can
+/// be undone atomically if a later change fails partway through.
+struct Transaction {
+    backup_dir: PathBuf,
+    /// Each touched path, and whether it existed (and so has a backup copy) before
+    /// the batch started.
+    snapshots: Vec<(PathBuf, bool)>,
+    no_rollback: bool,
+}
+
+impl Transaction {
+    fn begin(backup_dir: PathBuf, no_rollback: bool) -> Self {
+        Transaction {
+            backup_dir,
+            snapshots: Vec::new(),
+            no_rollback,
+        }
+    }
+
+    /// Back up `path`'s current contents, if any. Safe to call more than once for
+    /// the same path; only the first call takes a snapshot. Refuses to touch
+    /// paths outside the current directory: `backup_dir.join(path)` discards the
+    /// backup prefix for an absolute path, which would make the "backup" alias
+    /// the original file and `fs::copy` truncate it onto itself.
+    fn snapshot(&mut self, path: &Path) -> Result<()> {
+        if !is_file_in_current_directory(path) {
+            bail!("refusing to snapshot path outside the current directory: {:?}", path);
+        }
+
+        if self.no_rollback || self.snapshots.iter().any(|(p, _)| p == path) {
+            return Ok(());
+        }
+
+        let existed = path.exists();
+        if existed {
+            let backup_path = self.backup_dir.join(path);
+            if let Some(parent) = backup_path.parent() {
+                fs::create_dir_all(parent)
+                    .with_context(|| format!("Failed to create backup directory: {:?}", parent))?;
+            }
+            fs::copy(path, &backup_path)
+                .with_context(|| format!("Failed to back up {:?}", path))?;
+        }
+
+        self.snapshots.push((path.to_path_buf(), existed));
+        Ok(())
+    }
+
+    /// Restore every snapshotted path to the state it was in before the batch started.
+    fn rollback(&self) -> Result<()> {
+        for (path, existed) in &self.snapshots {
+            if *existed {
+                let backup_path = self.backup_dir.join(path);
+                fs::copy(&backup_path, path)
+                    .with_context(|| format!("Failed to restore {:?} from backup", path))?;
+            } else if path.exists() {
+                fs::remove_file(path)
+                    .with_context(|| format!("Failed to remove {:?} during rollback", path))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Ask the user a yes/no question on the controlling terminal, defaulting to "no".
+///
+/// Reads from `/dev/tty` rather than stdin because stdin may already have been
+/// consumed by the LLM response payload at this point.
+fn prompt_confirm(question: &str) -> Result<bool> {
+    print!("{question} [y/N] ");
+    io::Write::flush(&mut io::stdout()).ok();
+
+    let tty = match fs::File::open("/dev/tty") {
+        Ok(tty) => tty,
+        Err(_) => return Ok(false),
+    };
+    let mut answer = String::new();
+    io::BufReader::new(tty).read_line(&mut answer).ok();
+    Ok(matches!(
+        answer.trim().to_lowercase().as_str(),
+        "y" | "yes"
+    ))
+}
+
+enum DiffOp {
+    Equal(String),
+    Delete(String),
+    Insert(String),
+}
+
+/// Line-level LCS diff between `old` and `new`.
+fn diff_ops(old: &[String], new: &[String]) -> Vec<DiffOp> {
+    let n = old.len();
+    let m = new.len();
+    let mut dp = vec![vec![0usize; m + 1]; n + 1];
+    for i in (0..n).rev() {
+        for j in (0..m).rev() {
+            dp[i][j] = if old[i] == new[j] {
+                dp[i + 1][j + 1] + 1
+            } else {
+                dp[i + 1][j].max(dp[i][j + 1])
+            };
+        }
+    }
+
+    let mut ops = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < n && j < m {
+        if old[i] == new[j] {
+            ops.push(DiffOp::Equal(old[i].clone()));
+            i += 1;
+            j += 1;
+        } else if dp[i + 1][j] >= dp[i][j + 1] {
+            ops.push(DiffOp::Delete(old[i].clone()));
+            i += 1;
+        } else {
+            ops.push(DiffOp::Insert(new[j].clone()));
+            j += 1;
+        }
+    }
+    while i < n {
+        ops.push(DiffOp::Delete(old[i].clone()));
+        i += 1;
+    }
+    while j < m {
+        ops.push(DiffOp::Insert(new[j].clone()));
+        j += 1;
+    }
+
+    ops
+}
+
+/// Render a unified diff between `old` and `new`, grouping differing lines into
+/// `@@ -start,len +start,len @@` hunks with `context` surrounding lines of context.
+fn unified_diff(path: &Path, old: &[String], new: &[String], context: usize) -> String {
+    let ops = diff_ops(old, new);
+
+    let mut old_no = 0usize;
+    let mut new_no = 0usize;
+    let mut line_no_after = Vec::with_capacity(ops.len());
+    for op in &ops {
+        match op {
+            DiffOp::Equal(_) => {
+                old_no += 1;
+                new_no += 1;
+            }
+            DiffOp::Delete(_) => old_no += 1,
+            DiffOp::Insert(_) => new_no += 1,
+        }
+        line_no_after.push((old_no, new_no));
+    }
+
+    let changed: Vec<usize> = ops
+        .iter()
+        .enumerate()
+        .filter(|(_, op)| !matches!(op, DiffOp::Equal(_)))
+        .map(|(idx, _)| idx)
+        .collect();
+
+    if changed.is_empty() {
+        return String::new();
+    }
+
+    let mut groups: Vec<(usize, usize)> = Vec::new();
+    let mut group_start = changed[0];
+    let mut group_end = changed[0];
+    for &idx in &changed[1..] {
+        if idx - group_end <= 2 * context {
+            group_end = idx;
+        } else {
+            groups.push((group_start, group_end));
+            group_start = idx;
+            group_end = idx;
+        }
+    }
+    groups.push((group_start, group_end));
+
+    let mut out = format!("--- a/{}\n+++ b/{}\n", path.display(), path.display());
+    for (start, end) in groups {
+        let hunk_start = start.saturating_sub(context);
+        let hunk_end = (end + context + 1).min(ops.len());
+        let slice = &ops[hunk_start..hunk_end];
+
+        let (old_start, new_start) = if hunk_start == 0 {
+            (1, 1)
+        } else {
+            let (o, n) = line_no_after[hunk_start - 1];
+            (o + 1, n + 1)
+        };
+        let old_len = slice
+            .iter()
+            .filter(|op| !matches!(op, DiffOp::Insert(_)))
+            .count();
+        let new_len = slice
+            .iter()
+            .filter(|op| !matches!(op, DiffOp::Delete(_)))
+            .count();
+
+        out.push_str(&format!(
+            "@@ -{old_start},{old_len} +{new_start},{new_len} @@\n"
+        ));
+        for op in slice {
+            match op {
+                DiffOp::Equal(line) => out.push_str(&format!(" {line}\n")),
+                DiffOp::Delete(line) => out.push_str(&format!("-{line}\n")),
+                DiffOp::Insert(line) => out.push_str(&format!("+{line}\n")),
+            }
+        }
+    }
+
+    out
+}
+
+/// Either write `new_lines` to `filename` or, in dry-run mode, preview the change
+/// without touching disk. Returns whether the change was actually applied.
+fn finalize_change(
+    filename: &Path,
+    old_lines: &[String],
+    new_lines: &[String],
+    args: &CliArgs,
+) -> Result<bool> {
+    trace!("old lines for {}: {:?}", filename.display(), old_lines);
+    trace!("new lines for {}: {:?}", filename.display(), new_lines);
+
+    if args.dry_run || args.diff {
+        if args.diff {
+            let diff = unified_diff(filename, old_lines, new_lines, args.context);
+            if diff.is_empty() {
+                println!("(no changes)");
+            } else {
+                print!("{diff}");
+            }
+        } else {
+            println!(
+                "Would write {} lines to {} (dry run)",
+                new_lines.len(),
+                filename.display()
+            );
+        }
+    }
+
+    if args.dry_run {
+        return Ok(false);
+    }
+
+    if args.confirm && !prompt_confirm(&format!("Apply changes to {}?", filename.display()))? {
+        println!("Skipped (not confirmed): {}", filename.display());
+        return Ok(false);
+    }
+
+    fs::write(filename, new_lines.join("\n"))
+        .with_context(|| format!("✗ Failed to write to file: {:?}", filename))?;
+    Ok(true)
 }
 
 fn main() -> Result<()> {
-    let mut input = String::new();
-    io::stdin()
-        .read_to_string(&mut input)
-        .context("Failed to read from stdin")?;
+    env_logger::init();
 
-    let input = input
-        .split_once("{")
-        .map(|(_, v)| "{".to_string() + v)
-        .unwrap_or(input);
+    let cli_args = parse_args();
 
-    // Save the stdin data to a file in the llm2fs_changes directory
+    let raw_inputs: Vec<String> = if let Some(name) = &cli_args.replay {
+        let path = resolve_replay_path(name);
+        let saved = fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read replay file: {:?}", path))?;
+        saved.split(INPUT_SEPARATOR).map(str::to_string).collect()
+    } else if !cli_args.inputs.is_empty() {
+        cli_args
+            .inputs
+            .iter()
+            .map(|path| {
+                fs::read_to_string(path)
+                    .with_context(|| format!("Failed to read input file: {:?}", path))
+            })
+            .collect::<Result<Vec<_>>>()?
+    } else {
+        let mut input = String::new();
+        io::stdin()
+            .read_to_string(&mut input)
+            .context("Failed to read from stdin")?;
+        vec![input]
+    };
+
+    let responses = raw_inputs
+        .iter()
+        .map(|input| {
+            let input = input
+                .split_once('{')
+                .map(|(_, v)| "{".to_string() + v)
+                .unwrap_or_else(|| input.clone());
+            serde_json::from_str::<LLMResponse>(&input).context("Failed to parse JSON content")
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let response = if responses.len() > 1 {
+        merge_responses(responses)
+    } else {
+        responses.into_iter().next().expect("raw_inputs is never empty")
+    };
+
+    // Save the input to a file in the llm2fs_changes directory, unless this run
+    // is itself a replay of one already sitting there.
     let changes_dir = Path::new("llm2fs_changes");
     fs::create_dir_all(changes_dir).context("Failed to create llm2fs_changes directory")?;
 
     let timestamp = chrono::Local::now().format("%Y-%m-%d-%H-%M-%S").to_string();
-    let filename = format!("{}.txt", timestamp);
-    let file_path = changes_dir.join(filename);
+    if cli_args.replay.is_none() {
+        let file_path = changes_dir.join(format!("{timestamp}.txt"));
+        fs::write(&file_path, raw_inputs.join(INPUT_SEPARATOR))
+            .with_context(|| format!("Failed to save input data to {:?}", file_path))?;
+    }
+
+    let backup_dir = changes_dir.join(format!("{timestamp}-backup"));
+    let mut tx = Transaction::begin(backup_dir, cli_args.no_rollback);
+    if !cli_args.dry_run {
+        for change in &response.changes {
+            if !is_file_in_current_directory(&change.filename) {
+                continue;
+            }
+            tx.snapshot(&change.filename)?;
+            if let Command::RenameFile { new_filename } = &change.command {
+                if is_file_in_current_directory(new_filename) {
+                    tx.snapshot(new_filename)?;
+                }
+            }
+        }
+    }
 
-    fs::write(&file_path, &input)
-        .with_context(|| format!("Failed to save stdin data to {:?}", file_path))?;
+    if !cli_args.json {
+        println!("{}\n------", response.explanation);
+    }
 
-    let response: LLMResponse =
-        serde_json::from_str(&input).context("Failed to parse JSON content")?;
+    let run_start = Instant::now();
+    let mut reports = Vec::new();
+    let result = apply_changes(&response, &cli_args, &mut reports);
 
-    println!("{}\n------", response.explanation);
+    let mut rolled_back = false;
+    if let Err(err) = &result {
+        if cli_args.no_rollback {
+            eprintln!("⚠ A change failed; leaving the working tree as-is (--no-rollback): {err}");
+        } else {
+            eprintln!("✗ A change failed, rolling back to the pre-batch snapshot: {err}");
+            tx.rollback()?;
+            rolled_back = true;
+            for report in reports.iter_mut() {
+                if matches!(report.status, ChangeStatus::Applied) {
+                    report.status = ChangeStatus::RolledBack;
+                }
+            }
+        }
+    }
+
+    let applied = reports
+        .iter()
+        .filter(|r| matches!(r.status, ChangeStatus::Applied))
+        .count();
+    let skipped = reports
+        .iter()
+        .filter(|r| matches!(r.status, ChangeStatus::Skipped))
+        .count();
+    let failed = reports
+        .iter()
+        .filter(|r| matches!(r.status, ChangeStatus::Failed))
+        .count();
+    info!(
+        "run complete in {:?}: {applied} applied, {skipped} skipped, {failed} failed, rolled_back={rolled_back}",
+        run_start.elapsed()
+    );
+
+    if cli_args.json {
+        let report = RunReport {
+            explanation: response.explanation.clone(),
+            conclusion: response.conclusion.clone(),
+            changes: reports,
+            rolled_back,
+        };
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&report)
+                .context("Failed to serialize JSON report")?
+        );
+    }
+
+    result?;
+
+    if !cli_args.json {
+        if !response.changes.is_empty() {
+            println!("------");
+        }
+        println!(" {}", response.conclusion);
+    }
+
+    Ok(())
+}
 
+/// Apply every `Change` in `response`, in order. On the first error, returns
+/// immediately without attempting later changes; the caller is responsible for
+/// rolling back whatever was already written.
+fn apply_changes(
+    response: &LLMResponse,
+    cli_args: &CliArgs,
+    reports: &mut Vec<ChangeReport>,
+) -> Result<()> {
     for change in &response.changes {
         if !is_file_in_current_directory(&change.filename) {
-            println!(
-                "Warning: Filename '{}' is outside the current directory. Skipping.",
+            warn!(
+                "Filename '{}' is outside the current directory. Skipping.",
                 change.filename.display()
             );
+            reports.push(ChangeReport {
+                filename: change.filename.clone(),
+                command: command_tag(&change.command),
+                status: ChangeStatus::Skipped,
+                reason: "filename is outside the current directory".to_string(),
+                lines_affected: 0,
+                similarity: None,
+            });
             continue;
         }
 
-        println!();
-
-        println!("=> File: {}", change.filename.display());
-        println!(
-            "=> Action: {}",
+        let change_start = Instant::now();
+        info!(
+            "=> File: {} | Action: {} | Reason: {}",
+            change.filename.display(),
             match change.command {
                 Command::InsertBefore { .. } => "Inserting new lines before a marker",
                 Command::InsertAfter { .. } => "Inserting new lines after a marker",
@@ -115,55 +709,134 @@ fn main() -> Result<()> {
                 Command::CreateFile { .. } => "Creating a new file",
                 Command::RenameFile { .. } => "Renaming a file",
                 Command::DeleteFile => "Deleting a file",
-            }
+            },
+            change.reason
         );
-        println!("=> Reason: {}", change.reason);
 
         match &change.command {
             Command::CreateFile { new_lines } => {
                 let file_path = Path::new(&change.filename);
                 if file_path.exists() {
+                    reports.push(ChangeReport {
+                        filename: change.filename.clone(),
+                        command: command_tag(&change.command),
+                        status: ChangeStatus::Failed,
+                        reason: format!("file already exists: {:?}", change.filename),
+                        lines_affected: 0,
+                        similarity: None,
+                    });
                     bail!("File already exists: {:?}", change.filename);
                 }
-                if let Some(parent) = file_path.parent() {
-                    fs::create_dir_all(parent)
-                        .with_context(|| format!("✗ Failed to create directory: {:?}", parent))?;
+                let new_lines = new_lines.lines();
+                if !cli_args.dry_run {
+                    if let Some(parent) = file_path.parent() {
+                        fs::create_dir_all(parent).with_context(|| {
+                            format!("✗ Failed to create directory: {:?}", parent)
+                        })?;
+                    }
+                }
+                let applied = finalize_change(file_path, &[], &new_lines, cli_args)?;
+                if applied {
+                    info!(
+                        "✓ Created file {} and inserted {} lines",
+                        change.filename.display(),
+                        new_lines.len()
+                    );
                 }
-                fs::write(file_path, new_lines.lines().join("\n")).with_context(|| {
-                    format!("✗ Failed to create file: {}", change.filename.display())
-                })?;
-                println!(
-                    "✓ Created file {} and inserted {} lines",
-                    change.filename.display(),
-                    new_lines.len()
-                );
+                debug!("{} took {:?}", command_tag(&change.command), change_start.elapsed());
+                reports.push(ChangeReport {
+                    filename: change.filename.clone(),
+                    command: command_tag(&change.command),
+                    status: if applied {
+                        ChangeStatus::Applied
+                    } else {
+                        ChangeStatus::Skipped
+                    },
+                    reason: change.reason.clone(),
+                    lines_affected: new_lines.len(),
+                    similarity: None,
+                });
             }
             Command::RenameFile { new_filename } => {
-                fs::rename(&change.filename, new_filename).with_context(|| {
-                    format!("✗ Failed to rename file: {}", change.filename.display())
-                })?;
-                println!(
-                    "✓ Renamed file: {} -> {}",
-                    change.filename.display(),
-                    new_filename.display()
-                );
+                if cli_args.dry_run {
+                    println!(
+                        "Would rename {} -> {} (dry run)",
+                        change.filename.display(),
+                        new_filename.display()
+                    );
+                } else {
+                    fs::rename(&change.filename, new_filename).with_context(|| {
+                        format!("✗ Failed to rename file: {}", change.filename.display())
+                    })?;
+                    info!(
+                        "✓ Renamed file: {} -> {}",
+                        change.filename.display(),
+                        new_filename.display()
+                    );
+                }
+                debug!("{} took {:?}", command_tag(&change.command), change_start.elapsed());
+                reports.push(ChangeReport {
+                    filename: change.filename.clone(),
+                    command: command_tag(&change.command),
+                    status: if cli_args.dry_run {
+                        ChangeStatus::Skipped
+                    } else {
+                        ChangeStatus::Applied
+                    },
+                    reason: change.reason.clone(),
+                    lines_affected: 0,
+                    similarity: None,
+                });
             }
             Command::DeleteFile => {
-                fs::remove_file(&change.filename)
-                    .with_context(|| format!("✗ Failed to delete file: {:?}", change.filename))?;
-                println!("✓ Deleted file: {:?}", change.filename);
+                if cli_args.dry_run {
+                    println!("Would delete {} (dry run)", change.filename.display());
+                } else {
+                    fs::remove_file(&change.filename).with_context(|| {
+                        format!("✗ Failed to delete file: {:?}", change.filename)
+                    })?;
+                    info!("✓ Deleted file: {:?}", change.filename);
+                }
+                debug!("{} took {:?}", command_tag(&change.command), change_start.elapsed());
+                reports.push(ChangeReport {
+                    filename: change.filename.clone(),
+                    command: command_tag(&change.command),
+                    status: if cli_args.dry_run {
+                        ChangeStatus::Skipped
+                    } else {
+                        ChangeStatus::Applied
+                    },
+                    reason: change.reason.clone(),
+                    lines_affected: 0,
+                    similarity: None,
+                });
             }
             Command::InsertBefore {
                 insert_lines,
                 marker_lines,
             } => {
-                let file_lines = fs::read_to_string(&change.filename)
-                    .with_context(|| format!("✗ Failed to read file: {:?}", change.filename))?
-                    .lines()
-                    .map(String::from)
-                    .collect::<Vec<_>>();
+                let read_start = Instant::now();
+                let content = fs::read_to_string(&change.filename)
+                    .with_context(|| format!("✗ Failed to read file: {:?}", change.filename))?;
+                let read_elapsed = read_start.elapsed();
+                if let Some(reason) = check_stale_context(change, &content) {
+                    warn!("⚠ Skipping {}: {}", change.filename.display(), reason);
+                    reports.push(ChangeReport {
+                        filename: change.filename.clone(),
+                        command: command_tag(&change.command),
+                        status: ChangeStatus::Skipped,
+                        reason,
+                        lines_affected: insert_lines.len(),
+                        similarity: None,
+                    });
+                    continue;
+                }
+                let file_lines = content.lines().map(String::from).collect::<Vec<_>>();
+                let match_start = Instant::now();
+                let found = find_in_file_lines(&file_lines, &marker_lines.lines());
+                let match_elapsed = match_start.elapsed();
 
-                if let Some(index) = find_in_file_lines(&file_lines, &marker_lines.lines()) {
+                if let Some(index) = found.index() {
                     let mut insert_lines = insert_lines.lines();
                     let marker_lines = marker_lines.lines();
 
@@ -181,43 +854,107 @@ fn main() -> Result<()> {
                     let mut new_lines = file_lines[..index].to_vec();
                     new_lines.extend(insert_lines.clone());
                     new_lines.extend(file_lines[index..].iter().cloned());
-                    fs::write(&change.filename, new_lines.join("\n")).with_context(|| {
-                        format!("✗ Failed to write to file: {:?}", change.filename)
-                    })?;
-                    println!(
-                        "✓ Inserted {} lines into {}",
-                        insert_lines.len(),
+                    let write_start = Instant::now();
+                    let applied =
+                        finalize_change(&change.filename, &file_lines, &new_lines, cli_args)?;
+                    let write_elapsed = write_start.elapsed();
+                    if applied {
+                        info!(
+                            "✓ Inserted {} lines into {}",
+                            insert_lines.len(),
+                            change.filename.display()
+                        );
+                    }
+                    debug!(
+                        "{} for {}: read={read_elapsed:?} match={match_elapsed:?} write={write_elapsed:?}",
+                        command_tag(&change.command),
                         change.filename.display()
                     );
+                    reports.push(ChangeReport {
+                        filename: change.filename.clone(),
+                        command: command_tag(&change.command),
+                        status: if applied {
+                            ChangeStatus::Applied
+                        } else {
+                            ChangeStatus::Skipped
+                        },
+                        reason: change.reason.clone(),
+                        lines_affected: insert_lines.len(),
+                        similarity: found.similarity(),
+                    });
                 } else {
-                    bail!(
-                        "Failed to find {} lines in {:?}",
-                        marker_lines.len(),
-                        change.filename.display()
-                    );
+                    let reason = describe_miss(&found, "marker lines");
+                    reports.push(ChangeReport {
+                        filename: change.filename.clone(),
+                        command: command_tag(&change.command),
+                        status: ChangeStatus::Failed,
+                        reason: reason.clone(),
+                        lines_affected: marker_lines.len(),
+                        similarity: found.similarity(),
+                    });
+                    bail!("{} in {:?}", reason, change.filename);
                 }
             }
             Command::InsertAfter {
                 marker_lines,
                 insert_lines,
             } => {
-                let file_lines = fs::read_to_string(&change.filename)
-                    .with_context(|| format!("✗ Failed to read file: {:?}", change.filename))?
-                    .lines()
-                    .map(String::from)
-                    .collect::<Vec<_>>();
+                let read_start = Instant::now();
+                let content = fs::read_to_string(&change.filename)
+                    .with_context(|| format!("✗ Failed to read file: {:?}", change.filename))?;
+                let read_elapsed = read_start.elapsed();
+                if let Some(reason) = check_stale_context(change, &content) {
+                    warn!("⚠ Skipping {}: {}", change.filename.display(), reason);
+                    reports.push(ChangeReport {
+                        filename: change.filename.clone(),
+                        command: command_tag(&change.command),
+                        status: ChangeStatus::Skipped,
+                        reason,
+                        lines_affected: insert_lines.len(),
+                        similarity: None,
+                    });
+                    continue;
+                }
+                let file_lines = content.lines().map(String::from).collect::<Vec<_>>();
 
                 if marker_lines.len() == 0 && file_lines.is_empty() {
                     // This is the start of a new file
-                    fs::write(&change.filename, insert_lines.lines().join("\n")).with_context(
-                        || format!("✗ Failed to write to file: {:?}", change.filename),
-                    )?;
-                    println!(
-                        "✓ Inserted {} lines into {}",
-                        insert_lines.len(),
+                    let new_lines = insert_lines.lines();
+                    let write_start = Instant::now();
+                    let applied =
+                        finalize_change(&change.filename, &file_lines, &new_lines, cli_args)?;
+                    let write_elapsed = write_start.elapsed();
+                    if applied {
+                        info!(
+                            "✓ Inserted {} lines into {}",
+                            new_lines.len(),
+                            change.filename.display()
+                        );
+                    }
+                    debug!(
+                        "{} for {}: read={read_elapsed:?} match=n/a write={write_elapsed:?}",
+                        command_tag(&change.command),
                         change.filename.display()
                     );
-                } else if let Some(index) = find_in_file_lines(&file_lines, &marker_lines.lines()) {
+                    reports.push(ChangeReport {
+                        filename: change.filename.clone(),
+                        command: command_tag(&change.command),
+                        status: if applied {
+                            ChangeStatus::Applied
+                        } else {
+                            ChangeStatus::Skipped
+                        },
+                        reason: change.reason.clone(),
+                        lines_affected: new_lines.len(),
+                        similarity: None,
+                    });
+                    continue;
+                }
+
+                let match_start = Instant::now();
+                let found = find_in_file_lines(&file_lines, &marker_lines.lines());
+                let match_elapsed = match_start.elapsed();
+                if let Some(index) = found.index() {
                     let mut insert_lines = insert_lines.lines();
                     let marker_lines = marker_lines.lines();
 
@@ -235,63 +972,122 @@ fn main() -> Result<()> {
                     let mut new_lines = file_lines[..=index + marker_lines.len() - 1].to_vec();
                     new_lines.extend(insert_lines.clone());
                     new_lines.extend(file_lines[index + marker_lines.len()..].iter().cloned());
-                    fs::write(&change.filename, new_lines.join("\n")).with_context(|| {
-                        format!("✗ Failed to write to file: {:?}", change.filename)
-                    })?;
-                    println!(
-                        "✓ Inserted {} lines into {}",
-                        insert_lines.len(),
+                    let write_start = Instant::now();
+                    let applied =
+                        finalize_change(&change.filename, &file_lines, &new_lines, cli_args)?;
+                    let write_elapsed = write_start.elapsed();
+                    if applied {
+                        info!(
+                            "✓ Inserted {} lines into {}",
+                            insert_lines.len(),
+                            change.filename.display()
+                        );
+                    }
+                    debug!(
+                        "{} for {}: read={read_elapsed:?} match={match_elapsed:?} write={write_elapsed:?}",
+                        command_tag(&change.command),
                         change.filename.display()
                     );
+                    reports.push(ChangeReport {
+                        filename: change.filename.clone(),
+                        command: command_tag(&change.command),
+                        status: if applied {
+                            ChangeStatus::Applied
+                        } else {
+                            ChangeStatus::Skipped
+                        },
+                        reason: change.reason.clone(),
+                        lines_affected: insert_lines.len(),
+                        similarity: found.similarity(),
+                    });
                 } else {
-                    bail!(
-                        "Failed to find {} lines in {:?}",
-                        marker_lines.len(),
-                        change.filename.display()
-                    );
+                    let reason = describe_miss(&found, "marker lines");
+                    reports.push(ChangeReport {
+                        filename: change.filename.clone(),
+                        command: command_tag(&change.command),
+                        status: ChangeStatus::Failed,
+                        reason: reason.clone(),
+                        lines_affected: marker_lines.len(),
+                        similarity: found.similarity(),
+                    });
+                    bail!("{} in {:?}", reason, change.filename);
                 }
             }
             Command::Delete { delete_lines } => {
-                let file_lines = fs::read_to_string(&change.filename)
-                    .with_context(|| format!("✗ Failed to read file: {:?}", change.filename))?
-                    .lines()
-                    .map(String::from)
-                    .collect::<Vec<_>>();
+                let read_start = Instant::now();
+                let content = fs::read_to_string(&change.filename)
+                    .with_context(|| format!("✗ Failed to read file: {:?}", change.filename))?;
+                let read_elapsed = read_start.elapsed();
+                if let Some(reason) = check_stale_context(change, &content) {
+                    warn!("⚠ Skipping {}: {}", change.filename.display(), reason);
+                    reports.push(ChangeReport {
+                        filename: change.filename.clone(),
+                        command: command_tag(&change.command),
+                        status: ChangeStatus::Skipped,
+                        reason,
+                        lines_affected: delete_lines.len(),
+                        similarity: None,
+                    });
+                    continue;
+                }
+                let file_lines = content.lines().map(String::from).collect::<Vec<_>>();
 
-                dbg!(&delete_lines.lines());
+                debug!("lines to delete: {:?}", delete_lines.lines());
 
-                if let Some(start_index) = find_in_file_lines(&file_lines, &delete_lines.lines()) {
+                let match_start = Instant::now();
+                let found = find_in_file_lines(&file_lines, &delete_lines.lines());
+                let match_elapsed = match_start.elapsed();
+                if let Some(start_index) = found.index() {
                     let mut new_lines = file_lines[..start_index].to_vec();
                     new_lines.extend(
                         file_lines[start_index + delete_lines.lines().len()..]
                             .iter()
                             .cloned(),
                     );
-                    fs::write(&change.filename, new_lines.join("\n")).with_context(|| {
-                        format!("✗ Failed to write to file: {:?}", change.filename)
-                    })?;
-                    println!(
-                        "✓ Deleted {} lines in {:?}",
-                        delete_lines.len(),
+                    let write_start = Instant::now();
+                    let applied =
+                        finalize_change(&change.filename, &file_lines, &new_lines, cli_args)?;
+                    let write_elapsed = write_start.elapsed();
+                    if applied {
+                        info!(
+                            "✓ Deleted {} lines in {:?}",
+                            delete_lines.len(),
+                            change.filename.display()
+                        );
+                    }
+                    debug!(
+                        "{} for {}: read={read_elapsed:?} match={match_elapsed:?} write={write_elapsed:?}",
+                        command_tag(&change.command),
                         change.filename.display()
                     );
+                    reports.push(ChangeReport {
+                        filename: change.filename.clone(),
+                        command: command_tag(&change.command),
+                        status: if applied {
+                            ChangeStatus::Applied
+                        } else {
+                            ChangeStatus::Skipped
+                        },
+                        reason: change.reason.clone(),
+                        lines_affected: delete_lines.len(),
+                        similarity: found.similarity(),
+                    });
                 } else {
-                    bail!(
-                        "Failed to find {} lines to delete in {:?}",
-                        delete_lines.len(),
-                        change.filename.display()
-                    );
+                    let reason = describe_miss(&found, "lines to delete");
+                    reports.push(ChangeReport {
+                        filename: change.filename.clone(),
+                        command: command_tag(&change.command),
+                        status: ChangeStatus::Failed,
+                        reason: reason.clone(),
+                        lines_affected: delete_lines.len(),
+                        similarity: found.similarity(),
+                    });
+                    bail!("{} in {:?}", reason, change.filename);
                 }
             }
         }
     }
 
-    if !response.changes.is_empty() {
-        println!("------");
-    }
-
-    println!(" {}", response.conclusion);
-
     Ok(())
 }
 
@@ -299,6 +1095,54 @@ fn is_file_in_current_directory(path: &Path) -> bool {
     path.is_relative() && !path.starts_with("..")
 }
 
+/// The wire-format tag (as accepted in `"command"`) for a `Command`, used to
+/// identify which kind of change a `ChangeReport` entry describes.
+fn command_tag(command: &Command) -> &'static str {
+    match command {
+        Command::InsertAfter { .. } => "INSERT_AFTER",
+        Command::InsertBefore { .. } => "INSERT_BEFORE",
+        Command::Delete { .. } => "DELETE",
+        Command::CreateFile { .. } => "CREATE_FILE",
+        Command::RenameFile { .. } => "RENAME_FILE",
+        Command::DeleteFile => "DELETE_FILE",
+    }
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "snake_case")]
+enum ChangeStatus {
+    Applied,
+    Skipped,
+    Failed,
+    /// Was `Applied` when the change ran, but the batch later failed and this
+    /// change was reverted along with the rest of the transaction.
+    RolledBack,
+}
+
+#[derive(Debug, Serialize)]
+struct ChangeReport {
+    filename: PathBuf,
+    command: &'static str,
+    status: ChangeStatus,
+    reason: String,
+    lines_affected: usize,
+    /// Similarity score `find_in_file_lines` computed for the chosen window (for a
+    /// failed match, the best window seen), only present for the fuzzy-matched
+    /// commands (InsertAfter/InsertBefore/Delete).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    similarity: Option<f64>,
+}
+
+#[derive(Debug, Serialize)]
+struct RunReport {
+    explanation: String,
+    conclusion: String,
+    changes: Vec<ChangeReport>,
+    /// Whether the batch failed partway through and everything already
+    /// applied was reverted to the pre-batch snapshot.
+    rolled_back: bool,
+}
+
 fn levenshtein_distance(s1: &str, s2: &str) -> usize {
     let len1 = s1.chars().count();
     let len2 = s2.chars().count();
@@ -307,8 +1151,8 @@ fn levenshtein_distance(s1: &str, s2: &str) -> usize {
     for (i, row) in matrix.iter_mut().enumerate() {
         row[0] = i;
     }
-    for j in 0..=len2 {
-        matrix[0][j] = j;
+    for (j, cell) in matrix[0].iter_mut().enumerate() {
+        *cell = j;
     }
 
     for (i, c1) in s1.chars().enumerate() {
@@ -324,54 +1168,142 @@ fn levenshtein_distance(s1: &str, s2: &str) -> usize {
     matrix[len1][len2]
 }
 
-fn find_in_file_lines(file_lines: &[String], needle: &[String]) -> Option<usize> {
-    let non_empty_needle: Vec<_> = needle
+/// The outcome of searching `file_lines` for a match of `needle`.
+enum MatchOutcome {
+    /// A single window either matched exactly (anchor match) or won the fuzzy
+    /// scoring pass with no other window tying it above the threshold.
+    Found(usize, f64),
+    /// Two or more windows each independently met the 95% similarity threshold,
+    /// so the caller can't safely guess which one the LLM meant. Carries the
+    /// conflicting windows' start indices.
+    Ambiguous(Vec<usize>),
+    /// No window met the threshold; carries the best score seen, if any.
+    NotFound(Option<f64>),
+}
+
+impl MatchOutcome {
+    fn index(&self) -> Option<usize> {
+        match self {
+            MatchOutcome::Found(i, _) => Some(*i),
+            _ => None,
+        }
+    }
+
+    fn similarity(&self) -> Option<f64> {
+        match self {
+            MatchOutcome::Found(_, s) => Some(*s),
+            MatchOutcome::NotFound(s) => *s,
+            MatchOutcome::Ambiguous(_) => None,
+        }
+    }
+}
+
+/// Human-readable explanation for why a `MatchOutcome` wasn't a confident `Found`.
+fn describe_miss(outcome: &MatchOutcome, kind: &str) -> String {
+    match outcome {
+        MatchOutcome::Found(..) => unreachable!("describe_miss called on a Found outcome"),
+        MatchOutcome::Ambiguous(indices) => format!(
+            "ambiguous {kind}: {} windows tied at or above the 95% similarity threshold (starting at lines {:?})",
+            indices.len(),
+            indices
+        ),
+        MatchOutcome::NotFound(Some(similarity)) => format!(
+            "could not find {kind} (best match similarity {:.1}%)",
+            similarity * 100.0
+        ),
+        MatchOutcome::NotFound(None) => format!("could not find {kind}"),
+    }
+}
+
+/// Line-level distance between two equal-length slices of already-trimmed
+/// lines: each differing line contributes 1 (for being non-matching) plus its
+/// own Levenshtein distance; identical lines contribute 0.
+fn line_distance(window: &[&str], needle: &[&str]) -> usize {
+    window
         .iter()
-        .map(|s| s.trim())
-        .filter(|s| !s.is_empty())
-        .collect();
+        .zip(needle.iter())
+        .map(|(w, n)| if w == n { 0 } else { 1 + levenshtein_distance(w, n) })
+        .sum()
+}
 
-    if non_empty_needle.is_empty() {
-        return Some(0);
+/// Search `file_lines` for `needle`. Markers whose (trimmed) lines are unique
+/// within the file are located directly by equality; only when that anchor is
+/// missing, ambiguous, or its surrounding block differs do we fall back to
+/// per-line edit-distance scoring of
+/// every window, which also lets us detect genuinely ambiguous fuzzy matches.
+fn find_in_file_lines(file_lines: &[String], needle: &[String]) -> MatchOutcome {
+    let needle_len = needle.len();
+    if needle_len == 0 {
+        return MatchOutcome::Found(0, 1.0);
     }
 
-    let needle_joined = non_empty_needle.join("\n");
-    let needle_len = needle_joined.chars().count();
-    let mut best_match = None;
-    let mut min_distance = usize::MAX;
+    let file_trimmed: Vec<&str> = file_lines.iter().map(|s| s.trim()).collect();
+    let needle_trimmed: Vec<&str> = needle.iter().map(|s| s.trim()).collect();
 
-    for (i, window) in file_lines.windows(needle.len()).enumerate() {
-        let window_joined = window
-            .iter()
-            .map(|s| s.trim())
-            .collect::<Vec<_>>()
-            .join("\n");
-        let distance = levenshtein_distance(&needle_joined, &window_joined);
-
-        if distance < min_distance {
-            min_distance = distance;
-            best_match = Some(i);
+    // Anchor on the needle's first non-empty line: if it's unique in the file,
+    // locate it directly and verify the surrounding block matches exactly.
+    if let Some((anchor_offset, anchor_line)) = needle_trimmed
+        .iter()
+        .enumerate()
+        .find(|(_, l)| !l.is_empty())
+    {
+        let occurrences = file_trimmed.iter().filter(|l| *l == anchor_line).count();
+        if occurrences == 1 {
+            if let Some(anchor_idx) = file_trimmed.iter().position(|l| l == anchor_line) {
+                if anchor_idx >= anchor_offset {
+                    let start = anchor_idx - anchor_offset;
+                    if start + needle_len <= file_trimmed.len()
+                        && file_trimmed[start..start + needle_len] == needle_trimmed[..]
+                    {
+                        return MatchOutcome::Found(start, 1.0);
+                    }
+                }
+            }
         }
+    }
 
-        if distance == 0 {
-            break; // Exact match found
-        }
+    if needle_len > file_trimmed.len() {
+        return MatchOutcome::NotFound(None);
     }
 
-    // Check if the best match meets the 95% similarity threshold
-    if let Some(i) = best_match {
-        let similarity = 1.0 - (min_distance as f64 / needle_len as f64);
+    // Fuzzy fallback: score every window by line-level distance.
+    let needle_chars: usize = needle_trimmed
+        .iter()
+        .filter(|l| !l.is_empty())
+        .map(|l| l.chars().count())
+        .sum::<usize>()
+        .max(1);
 
-        if similarity >= 0.95 {
-            return Some(i);
-        } else {
-            println!("Best match similarity: {}", similarity);
-            println!(
-                "Best match: {:?}",
-                &file_lines[i..min(i + needle.len(), file_lines.len())]
-            );
+    let scored: Vec<(usize, f64)> = file_trimmed
+        .windows(needle_len)
+        .enumerate()
+        .map(|(i, window)| {
+            let distance = line_distance(window, &needle_trimmed);
+            (i, 1.0 - (distance as f64 / needle_chars as f64))
+        })
+        .collect();
+
+    debug!("candidate windows (start index, similarity): {:?}", scored);
+
+    let passing: Vec<usize> = scored
+        .iter()
+        .filter(|(_, similarity)| *similarity >= 0.95)
+        .map(|(i, _)| *i)
+        .collect();
+
+    match passing.len() {
+        0 => {
+            let best = scored
+                .iter()
+                .map(|(_, s)| *s)
+                .fold(None, |acc: Option<f64>, s| Some(acc.map_or(s, |a| a.max(s))));
+            MatchOutcome::NotFound(best)
         }
+        1 => {
+            let index = passing[0];
+            let similarity = scored.iter().find(|(i, _)| *i == index).unwrap().1;
+            MatchOutcome::Found(index, similarity)
+        }
+        _ => MatchOutcome::Ambiguous(passing),
     }
-
-    None
 }